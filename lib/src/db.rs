@@ -0,0 +1,142 @@
+use chrono::Utc;
+use sqlx::{sqlite::SqlitePoolOptions, Row, SqlitePool};
+
+use crate::prediction::{Request, Response, Status};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("database error: {0}")]
+	Sql(#[from] sqlx::Error),
+
+	#[error("failed to (de)serialize persisted prediction state: {0}")]
+	Serde(#[from] serde_json::Error),
+}
+
+/// Durability backend for [`Prediction`](crate::prediction::Prediction)
+/// state. `Memory` is the default and matches the prior in-memory-only
+/// behavior; `Sqlite` survives process restarts and makes submission
+/// idempotent on `id`.
+#[derive(Clone)]
+pub enum Db {
+	Memory,
+	Sqlite(SqlitePool),
+}
+
+impl Db {
+	pub fn memory() -> Self {
+		Self::Memory
+	}
+
+	/// Connects to `url` (e.g. `sqlite://cog.db`), creating the schema if
+	/// it doesn't already exist.
+	pub async fn sqlite(url: &str) -> Result<Self, Error> {
+		let pool = SqlitePoolOptions::new().connect(url).await?;
+
+		sqlx::query(
+			"CREATE TABLE IF NOT EXISTS predictions (
+				id TEXT PRIMARY KEY,
+				request TEXT NOT NULL,
+				status TEXT NOT NULL,
+				response TEXT,
+				created_at TEXT NOT NULL,
+				updated_at TEXT NOT NULL
+			)",
+		)
+		.execute(&pool)
+		.await?;
+
+		let db = Self::Sqlite(pool);
+		db.reload_abandoned().await?;
+
+		Ok(db)
+	}
+
+	/// Writes (or overwrites) the current state of prediction `id`. Called
+	/// on every status transition in `Prediction::process`.
+	pub async fn record(
+		&self,
+		id: &str,
+		request: &Request,
+		status: Status,
+		response: Option<&Response>,
+	) -> Result<(), Error> {
+		let Self::Sqlite(pool) = self else {
+			return Ok(());
+		};
+
+		let request = serde_json::to_string(request)?;
+		let response = response.map(serde_json::to_string).transpose()?;
+		let now = Utc::now().to_rfc3339();
+
+		// Once a row reaches a terminal status it must stick, so a stale
+		// "processing" write racing in after a fast prediction's terminal
+		// write can't un-complete it and break resubmission idempotency.
+		sqlx::query(
+			"INSERT INTO predictions (id, request, status, response, created_at, updated_at)
+			 VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+			 ON CONFLICT(id) DO UPDATE SET status = ?3, response = ?4, updated_at = ?5
+			 WHERE predictions.status NOT IN ('succeeded', 'failed', 'canceled')",
+		)
+		.bind(id)
+		.bind(request)
+		.bind(status_label(status))
+		.bind(response)
+		.bind(now)
+		.execute(pool)
+		.await?;
+
+		Ok(())
+	}
+
+	/// Returns the stored response for `id` if it already reached a
+	/// terminal status, so submission can be treated as idempotent.
+	pub async fn load_terminal(&self, id: &str) -> Result<Option<Response>, Error> {
+		let Self::Sqlite(pool) = self else {
+			return Ok(None);
+		};
+
+		let row = sqlx::query("SELECT response FROM predictions WHERE id = ?1 AND response IS NOT NULL")
+			.bind(id)
+			.fetch_optional(pool)
+			.await?;
+
+		row.map(|row| serde_json::from_str(row.get::<String, _>("response").as_str()))
+			.transpose()
+			.map_err(Error::from)
+	}
+
+	/// On startup, marks every prediction still in a non-terminal status
+	/// as `Failed` (it was abandoned by the process that was running it)
+	/// and returns their ids.
+	pub async fn reload_abandoned(&self) -> Result<Vec<String>, Error> {
+		let Self::Sqlite(pool) = self else {
+			return Ok(Vec::new());
+		};
+
+		let rows = sqlx::query(
+			"SELECT id FROM predictions WHERE status IN ('starting', 'processing')",
+		)
+		.fetch_all(pool)
+		.await?;
+
+		let ids: Vec<String> = rows.iter().map(|row| row.get("id")).collect();
+
+		sqlx::query("UPDATE predictions SET status = 'failed', updated_at = ?1 WHERE status IN ('starting', 'processing')")
+			.bind(Utc::now().to_rfc3339())
+			.execute(pool)
+			.await?;
+
+		Ok(ids)
+	}
+}
+
+fn status_label(status: Status) -> &'static str {
+	match status {
+		Status::Idle => "idle",
+		Status::Starting => "starting",
+		Status::Processing => "processing",
+		Status::Succeeded => "succeeded",
+		Status::Failed => "failed",
+		Status::Canceled => "canceled",
+	}
+}
@@ -0,0 +1,176 @@
+use serde_json::Value;
+
+use crate::errors::ValidationErrorSet;
+
+const DATA_URI_PREFIX: &str = "data:";
+
+/// Resource caps enforced against `req.input` before a model runs, so an
+/// oversized or malformed payload fails with a structured 4xx instead of
+/// crashing (or stalling) the prediction.
+#[derive(Debug, Clone, Default)]
+pub struct InputLimits {
+	/// Total serialized size of `input`, in bytes.
+	pub max_total_bytes: Option<usize>,
+	/// Number of data-URI fields allowed across `input`.
+	pub max_file_count: Option<usize>,
+	/// Decoded size of any single data-URI field, in bytes.
+	pub max_file_bytes: Option<usize>,
+	/// If set, every data-URI field's declared MIME type must appear here.
+	pub allowed_mime_types: Option<Vec<String>>,
+}
+
+impl InputLimits {
+	pub fn validate(&self, input: &Value) -> Result<(), ValidationErrorSet> {
+		let mut errors = ValidationErrorSet::default();
+
+		if let Some(max_total_bytes) = self.max_total_bytes {
+			let size = serde_json::to_vec(input).map(|bytes| bytes.len()).unwrap_or(0);
+			if size > max_total_bytes {
+				errors.push(
+					&[],
+					format!("input is {size} bytes, which exceeds the {max_total_bytes} byte limit"),
+				);
+			}
+		}
+
+		let mut file_count = 0;
+		self.walk(input, &mut Vec::new(), &mut file_count, &mut errors);
+
+		if let Some(max_file_count) = self.max_file_count {
+			if file_count > max_file_count {
+				errors.push(
+					&[],
+					format!("input contains {file_count} file(s), which exceeds the limit of {max_file_count}"),
+				);
+			}
+		}
+
+		if errors.is_empty() {
+			Ok(())
+		} else {
+			Err(errors)
+		}
+	}
+
+	fn walk(
+		&self,
+		value: &Value,
+		path: &mut Vec<String>,
+		file_count: &mut usize,
+		errors: &mut ValidationErrorSet,
+	) {
+		match value {
+			Value::String(s) if s.starts_with(DATA_URI_PREFIX) => {
+				*file_count += 1;
+				self.validate_data_uri(s, path, errors);
+			}
+			Value::Object(map) => {
+				for (key, value) in map {
+					path.push(key.clone());
+					self.walk(value, path, file_count, errors);
+					path.pop();
+				}
+			}
+			Value::Array(items) => {
+				for (index, value) in items.iter().enumerate() {
+					path.push(index.to_string());
+					self.walk(value, path, file_count, errors);
+					path.pop();
+				}
+			}
+			_ => {}
+		}
+	}
+
+	fn validate_data_uri(&self, uri: &str, path: &[String], errors: &mut ValidationErrorSet) {
+		let loc: Vec<&str> = path.iter().map(String::as_str).collect();
+
+		let Some((header, data)) = uri[DATA_URI_PREFIX.len()..].split_once(',') else {
+			errors.push(&loc, "malformed data URI".to_string());
+			return;
+		};
+
+		let mime = header.split(';').next().unwrap_or_default();
+		if let Some(allowed) = &self.allowed_mime_types {
+			if !allowed.iter().any(|allowed| allowed == mime) {
+				errors.push(&loc, format!("media type '{mime}' is not allowed"));
+			}
+		}
+
+		if let Some(max_file_bytes) = self.max_file_bytes {
+			// Base64 expands data by 4/3; decoded size is close enough
+			// without actually paying to decode on every validation.
+			let decoded_estimate = data.len() * 3 / 4;
+			if decoded_estimate > max_file_bytes {
+				errors.push(
+					&loc,
+					format!(
+						"file is approximately {decoded_estimate} bytes, which exceeds the {max_file_bytes} byte limit"
+					),
+				);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	#[test]
+	fn passes_with_no_limits_configured() {
+		let limits = InputLimits::default();
+		assert!(limits.validate(&json!({"prompt": "hello"})).is_ok());
+	}
+
+	#[test]
+	fn rejects_input_over_max_total_bytes() {
+		let limits = InputLimits {
+			max_total_bytes: Some(8),
+			..Default::default()
+		};
+		assert!(limits.validate(&json!({"prompt": "this is far too long"})).is_err());
+	}
+
+	#[test]
+	fn rejects_too_many_files() {
+		let limits = InputLimits {
+			max_file_count: Some(1),
+			..Default::default()
+		};
+		let input = json!({
+			"a": "data:text/plain;base64,aGVsbG8=",
+			"b": "data:text/plain;base64,aGVsbG8=",
+		});
+		assert!(limits.validate(&input).is_err());
+	}
+
+	#[test]
+	fn rejects_disallowed_mime_type() {
+		let limits = InputLimits {
+			allowed_mime_types: Some(vec!["image/png".to_string()]),
+			..Default::default()
+		};
+		let input = json!({"file": "data:image/jpeg;base64,aGVsbG8="});
+		assert!(limits.validate(&input).is_err());
+	}
+
+	#[test]
+	fn rejects_file_over_max_file_bytes() {
+		let limits = InputLimits {
+			max_file_bytes: Some(2),
+			..Default::default()
+		};
+		let input = json!({"file": "data:text/plain;base64,aGVsbG8gd29ybGQ="});
+		assert!(limits.validate(&input).is_err());
+	}
+
+	#[test]
+	fn rejects_malformed_data_uri() {
+		let limits = InputLimits::default();
+		let input = json!({"file": "data:text/plain;base64-no-comma"});
+		assert!(limits.validate(&input).is_err());
+	}
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use prometheus::{
+	Encoder, Histogram, HistogramOpts, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+
+use crate::prediction::Status;
+
+/// Prometheus metrics aggregated across every prediction handled by this
+/// process, exposed in text format at `/metrics`.
+pub struct Metrics {
+	registry: Registry,
+	predictions_total: IntCounterVec,
+	in_flight: IntGauge,
+	queue_wait_seconds: Histogram,
+	predict_time_seconds: Histogram,
+	setup_time_seconds: Histogram,
+}
+
+impl Metrics {
+	pub fn new() -> Self {
+		let registry = Registry::new();
+
+		let predictions_total = IntCounterVec::new(
+			Opts::new(
+				"cog_predictions_total",
+				"Number of predictions that reached a terminal status",
+			),
+			&["status"],
+		)
+		.unwrap();
+
+		let in_flight = IntGauge::new(
+			"cog_predictions_in_flight",
+			"Number of predictions currently processing",
+		)
+		.unwrap();
+
+		let queue_wait_seconds = Histogram::with_opts(HistogramOpts::new(
+			"cog_queue_wait_seconds",
+			"Time a prediction spent between Starting and Processing",
+		))
+		.unwrap();
+
+		let predict_time_seconds = Histogram::with_opts(HistogramOpts::new(
+			"cog_predict_time_seconds",
+			"Time a prediction spent between Processing and a terminal status",
+		))
+		.unwrap();
+
+		let setup_time_seconds = Histogram::with_opts(HistogramOpts::new(
+			"cog_setup_time_seconds",
+			"Time spent in Cog::setup, recorded once at startup",
+		))
+		.unwrap();
+
+		for collector in [
+			Box::new(predictions_total.clone()) as Box<dyn prometheus::core::Collector>,
+			Box::new(in_flight.clone()),
+			Box::new(queue_wait_seconds.clone()),
+			Box::new(predict_time_seconds.clone()),
+			Box::new(setup_time_seconds.clone()),
+		] {
+			registry.register(collector).unwrap();
+		}
+
+		Self {
+			registry,
+			predictions_total,
+			in_flight,
+			queue_wait_seconds,
+			predict_time_seconds,
+			setup_time_seconds,
+		}
+	}
+
+	pub(crate) fn observe_queued(&self) {
+		self.in_flight.inc();
+	}
+
+	pub(crate) fn observe_queue_wait(&self, wait: Duration) {
+		self.queue_wait_seconds.observe(wait.as_secs_f64());
+	}
+
+	pub(crate) fn observe_terminal(&self, status: Status, predict_time: Duration) {
+		self.in_flight.dec();
+		self.predict_time_seconds.observe(predict_time.as_secs_f64());
+		self.predictions_total
+			.with_label_values(&[label(status)])
+			.inc();
+	}
+
+	/// Records the one-time cost of `Cog::setup`, for distinguishing
+	/// cold-start latency from steady-state predict time.
+	pub fn observe_setup(&self, setup_time: Duration) {
+		self.setup_time_seconds.observe(setup_time.as_secs_f64());
+	}
+
+	/// Renders the registry in Prometheus text exposition format.
+	pub fn render(&self) -> String {
+		let mut buffer = Vec::new();
+		TextEncoder::new()
+			.encode(&self.registry.gather(), &mut buffer)
+			.expect("metrics encode to valid utf8");
+
+		String::from_utf8(buffer).expect("prometheus output is utf8")
+	}
+}
+
+impl Default for Metrics {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+fn label(status: Status) -> &'static str {
+	match status {
+		Status::Succeeded => "succeeded",
+		Status::Failed => "failed",
+		Status::Canceled => "canceled",
+		Status::Starting | Status::Processing | Status::Idle => "unknown",
+	}
+}
+
+/// Axum handler for `GET /metrics`.
+pub async fn handler(
+	axum::Extension(metrics): axum::Extension<std::sync::Arc<Metrics>>,
+) -> String {
+	metrics.render()
+}
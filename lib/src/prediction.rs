@@ -1,4 +1,9 @@
-use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
+use std::{
+	collections::HashMap,
+	future::Future,
+	sync::Arc,
+	time::{Duration, Instant},
+};
 
 use chrono::{DateTime, Utc};
 use map_macro::hash_map;
@@ -9,9 +14,14 @@ use tokio::sync::RwLock;
 use url::Url;
 
 use crate::{
+	db::Db,
 	errors::ValidationErrorSet,
+	limits::InputLimits,
+	metrics::Metrics,
 	runner::{Error as RunnerError, Runner},
 	shutdown::Shutdown,
+	stream::PredictionStream,
+	webhook::Webhook,
 	Cog,
 };
 
@@ -30,6 +40,12 @@ pub enum Status {
 
 pub type Extension = axum::Extension<Arc<RwLock<Prediction>>>;
 
+/// Outcome of [`Prediction::init_idempotent`].
+pub enum InitOutcome {
+	Started,
+	AlreadyComplete(Response),
+}
+
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum Error {
 	#[error("Attempted to re-initialize a prediction")]
@@ -38,6 +54,9 @@ pub enum Error {
 	#[error("Prediction is not yet complete")]
 	NotComplete,
 
+	#[error("Prediction already reached a terminal status; nothing to cancel")]
+	AlreadyComplete,
+
 	#[error("The requested prediction does not exist")]
 	Unknown,
 
@@ -46,21 +65,60 @@ pub enum Error {
 
 	#[error("Failed to run prediction: {0}")]
 	Validation(#[from] ValidationErrorSet),
+
+	#[error("Failed to persist prediction: {0}")]
+	Persistence(#[from] crate::db::Error),
 }
 
 pub struct Prediction {
-	runner: Runner,
+	runner: Arc<Runner>,
 	status: Status,
 	pub id: Option<String>,
 	pub shutdown: Shutdown,
 	request: Option<Request>,
 	cancel: flume::Sender<()>,
+	cancel_rx: flume::Receiver<()>,
 	response: Option<Response>,
 	complete: Option<flume::Receiver<Response>>,
+	webhook_secret: Option<String>,
+	webhook: Option<Webhook>,
+	stream: PredictionStream,
+	metrics: Option<Arc<Metrics>>,
+	queued_at: Option<Instant>,
+	processing_at: Option<Instant>,
+	db: Db,
+	limits: InputLimits,
+	setup_time: Duration,
 }
 
 impl Prediction {
 	pub fn setup<T: Cog + 'static>(shutdown: Shutdown) -> Self {
+		Self::setup_with_webhook_secret::<T>(shutdown, None)
+	}
+
+	/// Like [`Prediction::setup`], but configures a shared secret used to
+	/// sign outgoing webhook deliveries with `X-Webhook-Signature`.
+	pub fn setup_with_webhook_secret<T: Cog + 'static>(
+		shutdown: Shutdown,
+		webhook_secret: Option<String>,
+	) -> Self {
+		let setup_started = Instant::now();
+		let runner = Arc::new(Runner::new::<T>());
+		let setup_time = setup_started.elapsed();
+
+		Self::from_runner(shutdown, webhook_secret, runner, setup_time)
+	}
+
+	/// Builds a `Prediction` around an already-constructed `Runner`, so
+	/// many predictions (e.g. the ones [`crate::store::PredictionStore`]
+	/// hands out) can share one warmed-up `Cog` instead of paying
+	/// `Cog::setup`'s cost again for every submission.
+	pub(crate) fn from_runner(
+		shutdown: Shutdown,
+		webhook_secret: Option<String>,
+		runner: Arc<Runner>,
+		setup_time: Duration,
+	) -> Self {
 		let (cancel_tx, cancel_rx) = flume::unbounded();
 
 		Self {
@@ -69,17 +127,75 @@ impl Prediction {
 			complete: None,
 			response: None,
 			status: Status::Idle,
-			shutdown: shutdown.clone(),
+			shutdown,
 			cancel: cancel_tx,
-			runner: Runner::new::<T>(shutdown, cancel_rx),
+			cancel_rx,
+			runner,
+			webhook_secret,
+			webhook: None,
+			stream: PredictionStream::default(),
+			metrics: None,
+			queued_at: None,
+			processing_at: None,
+			db: Db::memory(),
+			limits: InputLimits::default(),
+			setup_time,
 		}
 	}
 
+	/// Selects a durability backend for prediction state. Defaults to
+	/// in-memory only; pass [`Db::sqlite`] to survive process restarts and
+	/// make submission idempotent on `id`.
+	pub fn with_db(mut self, db: Db) -> Self {
+		self.db = db;
+		self
+	}
+
+	/// Bounds `input` size/count/type before a prediction is allowed to
+	/// run. Defaults to no limits.
+	pub fn with_limits(mut self, limits: InputLimits) -> Self {
+		self.limits = limits;
+		self
+	}
+
+	/// The channel backing this prediction's SSE endpoint: buffered
+	/// `logs`/`output` plus any events still to come.
+	pub fn stream(&self) -> PredictionStream {
+		self.stream.clone()
+	}
+
+	/// Attaches a metrics registry to track status counts, in-flight
+	/// gauges, and queue-wait/predict-time histograms for every run, and
+	/// records the cold-start cost of the `Cog::setup` that already ran
+	/// when this `Prediction` was constructed.
+	pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+		metrics.observe_setup(self.setup_time);
+		self.set_metrics(metrics);
+		self
+	}
+
+	/// Attaches a metrics registry without recording `setup_time` — for
+	/// callers (like [`crate::store::PredictionStore`]) that already
+	/// recorded the shared `Runner`'s one-time setup cost themselves and
+	/// would otherwise re-observe it on every prediction built around it.
+	pub(crate) fn set_metrics(&mut self, metrics: Arc<Metrics>) {
+		self.metrics = Some(metrics);
+	}
+
 	pub fn init(&mut self, id: Option<String>, req: Request) -> Result<&mut Self, Error> {
 		if !matches!(self.status, Status::Idle) {
 			return Err(Error::AlreadyRunning);
 		}
 
+		self.webhook = req.webhook.clone().map(|url| {
+			Webhook::new(
+				url,
+				req.webhook_event_filters.clone(),
+				self.webhook_secret.clone(),
+			)
+		});
+		self.stream = PredictionStream::default();
+		self.queued_at = Some(Instant::now());
 		self.id = id;
 		self.request = Some(req);
 		self.status = Status::Starting;
@@ -87,6 +203,45 @@ impl Prediction {
 		Ok(self)
 	}
 
+	/// A clone of the cancellation channel `Runner::run` selects on, for
+	/// callers (like [`crate::store::PredictionStore`]) that need to cancel
+	/// a run without contending for the write lock the run itself holds.
+	pub(crate) fn cancel_sender(&self) -> flume::Sender<()> {
+		self.cancel.clone()
+	}
+
+	/// Marks this prediction `Failed` with `error`, without having run it —
+	/// used when `process()` itself returns an error (e.g. input failed
+	/// validation) so `wait_for` surfaces it instead of reporting
+	/// `AlreadyRunning` forever.
+	pub(crate) fn fail(&mut self, error: &Error) {
+		let req = self
+			.request
+			.clone()
+			.expect("fail() is only called after init()");
+		self.status = Status::Failed;
+		self.response = Some(Response::failed(self.id.clone(), req, error.to_string()));
+	}
+
+	/// Like [`Prediction::init`], but treats `id` as an idempotency key
+	/// when a SQLite backend is configured: if `id` already reached a
+	/// terminal status, its stored response is returned instead of
+	/// starting a new run.
+	pub async fn init_idempotent(
+		&mut self,
+		id: Option<String>,
+		req: Request,
+	) -> Result<InitOutcome, Error> {
+		if let Some(id) = &id {
+			if let Some(response) = self.db.load_terminal(id).await? {
+				return Ok(InitOutcome::AlreadyComplete(response));
+			}
+		}
+
+		self.init(id, req)?;
+		Ok(InitOutcome::Started)
+	}
+
 	pub async fn run(&mut self) -> Result<Response, Error> {
 		self.process()?.await;
 
@@ -124,18 +279,44 @@ impl Prediction {
 		self.runner
 			.validate(&req.input)
 			.map_err(|e| e.fill_loc(&["body", "input"]))?;
+		self.limits
+			.validate(&req.input)
+			.map_err(|e| e.fill_loc(&["body", "input"]))?;
 
 		self.status = Status::Processing;
+		self.processing_at = Some(Instant::now());
+
+		if let Some(metrics) = &self.metrics {
+			metrics.observe_queued();
+			if let Some(queued_at) = self.queued_at {
+				metrics.observe_queue_wait(queued_at.elapsed());
+			}
+		}
+
+		if let Some(id) = self.id.clone() {
+			let db = self.db.clone();
+			let req = req.clone();
+			tokio::spawn(async move {
+				let _ = db.record(&id, &req, Status::Processing, None).await;
+			});
+		}
+
+		if let Some(webhook) = &self.webhook {
+			let starting = Response::processing(self.id.clone(), req.clone());
+			webhook.notify(WebhookEvent::Start, starting.clone());
+			webhook.follow(starting, self.stream());
+		}
 
 		let (complete_tx, complete_rx) = flume::bounded(1);
 		self.complete = Some(complete_rx);
+		let req_for_db = req.clone();
 
 		Ok(async move {
 			tokio::select! {
 				_ = self.shutdown.handle() => {
 					return;
 				},
-				output = self.runner.run(req.input.clone()) => {
+				output = self.runner.run(req.input.clone(), self.stream.sink(), self.cancel_rx.clone()) => {
 					match output {
 						Ok((output, predict_time)) => {
 							self.status = Status::Succeeded;
@@ -152,7 +333,33 @@ impl Prediction {
 					}
 				}
 			}
-			complete_tx.send(self.response.clone().unwrap()).unwrap();
+
+			if let Some(metrics) = &self.metrics {
+				let predict_time = self
+					.processing_at
+					.map(|at| at.elapsed())
+					.unwrap_or_default();
+				metrics.observe_terminal(self.status, predict_time);
+			}
+
+			let mut response = self.response.clone().unwrap();
+			response.logs = self.stream.logs().await;
+			self.response = Some(response.clone());
+
+			if let Some(id) = self.id.clone() {
+				let _ = self
+					.db
+					.record(&id, &req_for_db, self.status, Some(&response))
+					.await;
+			}
+			if let Some(output) = response.output.clone() {
+				self.stream.push_output(output).await;
+			}
+			self.stream.push_completed(response.clone()).await;
+			if let Some(webhook) = &self.webhook {
+				webhook.notify(WebhookEvent::Completed, response.clone());
+			}
+			complete_tx.send(response).unwrap();
 		})
 	}
 
@@ -187,6 +394,10 @@ impl Prediction {
 		self.request = None;
 		self.response = None;
 		self.complete = None;
+		self.webhook = None;
+		self.stream = PredictionStream::default();
+		self.queued_at = None;
+		self.processing_at = None;
 		self.status = Status::Idle;
 	}
 
@@ -221,7 +432,7 @@ impl Drop for SyncGuard<'_> {
 	}
 }
 
-#[derive(Debug, Clone, serde::Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, JsonSchema)]
 pub enum WebhookEvent {
 	Start,
 	Output,
@@ -258,6 +469,15 @@ pub struct Response<Req = Value, Res = Value> {
 }
 
 impl Response {
+	pub fn processing(id: Option<String>, req: Request) -> Self {
+		Self {
+			id,
+			input: Some(req.input),
+			status: Status::Processing,
+			..Self::default()
+		}
+	}
+
 	pub fn success(
 		id: Option<String>,
 		req: Request,
@@ -276,11 +496,15 @@ impl Response {
 		}
 	}
 	pub fn error(id: Option<String>, req: Request, error: &RunnerError) -> Self {
+		Self::failed(id, req, error.to_string())
+	}
+
+	pub fn failed(id: Option<String>, req: Request, error: String) -> Self {
 		Self {
 			id,
 			input: Some(req.input),
 			status: Status::Failed,
-			error: Some(error.to_string()),
+			error: Some(error),
 			..Self::default()
 		}
 	}
@@ -0,0 +1,97 @@
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::{errors::ValidationErrorSet, stream::PredictionStream, Cog};
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum Error {
+	#[error("prediction was canceled")]
+	Canceled,
+
+	#[error("input failed validation: {0}")]
+	Validation(#[from] ValidationErrorSet),
+
+	#[error("prediction failed: {0}")]
+	Predict(String),
+}
+
+/// Handle passed to `Cog::predict` so a running model can push partial
+/// output and append log lines as it works, backed by the same
+/// [`PredictionStream`] that drives the SSE endpoint and the
+/// `Output`/`Logs` webhook deliveries.
+#[derive(Clone)]
+pub struct Context {
+	stream: PredictionStream,
+}
+
+impl Context {
+	fn new(stream: PredictionStream) -> Self {
+		Self { stream }
+	}
+
+	pub async fn push_output(&self, output: Value) {
+		self.stream.push_output(output).await;
+	}
+
+	pub async fn log(&self, line: impl AsRef<str>) {
+		self.stream.push_logs(line.as_ref()).await;
+	}
+}
+
+#[async_trait::async_trait]
+trait ErasedCog: Send + Sync {
+	fn validate(&self, input: &Value) -> Result<(), ValidationErrorSet>;
+	async fn predict(&self, input: Value, ctx: Context) -> Result<Value, Error>;
+}
+
+#[async_trait::async_trait]
+impl<T: Cog> ErasedCog for T {
+	fn validate(&self, input: &Value) -> Result<(), ValidationErrorSet> {
+		Cog::validate(self, input)
+	}
+
+	async fn predict(&self, input: Value, ctx: Context) -> Result<Value, Error> {
+		Cog::predict(self, input, ctx).await
+	}
+}
+
+/// Wraps a single `Cog` instance, built once via `Cog::setup`. Holds no
+/// per-run state, so one `Runner` can be shared (typically via `Arc`) across
+/// many concurrent predictions instead of paying `setup`'s cost again for
+/// each one — cancellation is threaded through `run` per call instead.
+pub struct Runner {
+	cog: Box<dyn ErasedCog>,
+}
+
+impl Runner {
+	pub fn new<T: Cog + 'static>() -> Self {
+		Self {
+			cog: Box::new(T::setup()),
+		}
+	}
+
+	pub fn validate(&self, input: &Value) -> Result<(), ValidationErrorSet> {
+		self.cog.validate(input)
+	}
+
+	/// Runs a prediction to completion, pushing partial output and log
+	/// lines through `sink` as the model produces them via `Cog::predict`'s
+	/// [`Context`] argument, and returns the final output plus how long
+	/// `predict` took. `cancel` is selected against for the duration of this
+	/// one run and isn't retained afterward.
+	pub async fn run(
+		&self,
+		input: Value,
+		sink: PredictionStream,
+		cancel: flume::Receiver<()>,
+	) -> Result<(Value, Duration), Error> {
+		let started = Instant::now();
+		let ctx = Context::new(sink);
+
+		tokio::select! {
+			_ = cancel.recv_async() => Err(Error::Canceled),
+			result = self.cog.predict(input, ctx) => result.map(|output| (output, started.elapsed())),
+		}
+	}
+}
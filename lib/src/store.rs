@@ -0,0 +1,219 @@
+use std::{
+	collections::HashMap,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+use tokio::sync::{RwLock, Semaphore};
+use uuid::Uuid;
+
+use crate::{
+	db::Db,
+	limits::InputLimits,
+	metrics::Metrics,
+	prediction::{Error, Prediction, Request, Response},
+	runner::Runner,
+	shutdown::Shutdown,
+	Cog,
+};
+
+const DEFAULT_CONCURRENCY: usize = 4;
+const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+pub type Extension = axum::Extension<Arc<PredictionStore>>;
+
+struct Entry {
+	prediction: Arc<RwLock<Prediction>>,
+	cancel: flume::Sender<()>,
+	completed_at: Option<Instant>,
+}
+
+/// An id-keyed registry of predictions, replacing the single-slot model of
+/// [`Prediction`] with support for many concurrent/background runs.
+///
+/// Submissions beyond `concurrency` queue on an internal semaphore rather
+/// than failing, and completed entries are evicted after `ttl` elapses so
+/// the store doesn't grow unbounded.
+pub struct PredictionStore {
+	runner: Arc<Runner>,
+	setup_time: Duration,
+	shutdown: Shutdown,
+	entries: Arc<RwLock<HashMap<String, Entry>>>,
+	permits: Arc<Semaphore>,
+	ttl: Duration,
+	db: Db,
+	metrics: Option<Arc<Metrics>>,
+	limits: InputLimits,
+}
+
+impl PredictionStore {
+	pub fn new<T: Cog + 'static>(shutdown: Shutdown) -> Self {
+		Self::with_options::<T>(shutdown, DEFAULT_CONCURRENCY, DEFAULT_TTL)
+	}
+
+	/// Builds `T`'s `Cog` exactly once via `Runner::new` and shares it
+	/// (through an `Arc`) across every prediction this store hands out,
+	/// instead of re-running `Cog::setup` on each submission.
+	pub fn with_options<T: Cog + 'static>(
+		shutdown: Shutdown,
+		concurrency: usize,
+		ttl: Duration,
+	) -> Self {
+		let setup_started = Instant::now();
+		let runner = Arc::new(Runner::new::<T>());
+		let setup_time = setup_started.elapsed();
+
+		Self {
+			runner,
+			setup_time,
+			shutdown,
+			entries: Arc::new(RwLock::new(HashMap::new())),
+			permits: Arc::new(Semaphore::new(concurrency)),
+			ttl,
+			db: Db::memory(),
+			metrics: None,
+			limits: InputLimits::default(),
+		}
+	}
+
+	/// Durability backend applied to every prediction this store creates.
+	pub fn with_db(mut self, db: Db) -> Self {
+		self.db = db;
+		self
+	}
+
+	/// Metrics registry applied to every prediction this store creates.
+	/// Records the shared `Runner`'s one-time `Cog::setup` cost right here,
+	/// since it's now built once for the store rather than per submission.
+	pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+		metrics.observe_setup(self.setup_time);
+		self.metrics = Some(metrics);
+		self
+	}
+
+	/// Input limits applied to every prediction this store creates.
+	pub fn with_limits(mut self, limits: InputLimits) -> Self {
+		self.limits = limits;
+		self
+	}
+
+	fn new_prediction(&self) -> Prediction {
+		let mut prediction = Prediction::from_runner(
+			self.shutdown.clone(),
+			None,
+			self.runner.clone(),
+			Duration::ZERO,
+		)
+		.with_db(self.db.clone())
+		.with_limits(self.limits.clone());
+
+		if let Some(metrics) = &self.metrics {
+			prediction.set_metrics(metrics.clone());
+		}
+
+		prediction
+	}
+
+	/// Runs a prediction to completion and returns its response, as
+	/// [`Prediction::run`] did, but dispatched through the store so it
+	/// shares the concurrency limit with background submissions.
+	pub async fn run(&self, id: Option<String>, req: Request) -> Result<Response, Error> {
+		let id = self.submit(id, req).await?;
+		self.wait_for(id).await
+	}
+
+	/// Registers `req` under `id` (generating one if absent) and spawns it
+	/// onto the worker pool, returning immediately with the assigned id.
+	pub async fn submit(&self, id: Option<String>, req: Request) -> Result<String, Error> {
+		self.evict_expired().await;
+
+		let id = id.unwrap_or_else(|| Uuid::new_v4().to_string());
+		let mut prediction = self.new_prediction();
+		let cancel = prediction.init(Some(id.clone()), req)?.cancel_sender();
+		let prediction = Arc::new(RwLock::new(prediction));
+
+		self.entries.write().await.insert(
+			id.clone(),
+			Entry {
+				prediction: prediction.clone(),
+				cancel,
+				completed_at: None,
+			},
+		);
+
+		let permits = self.permits.clone();
+		let entries = self.entries.clone();
+		let entry_id = id.clone();
+
+		tokio::spawn(async move {
+			let _permit = permits.acquire_owned().await.expect("semaphore closed");
+
+			{
+				let mut guard = prediction.write().await;
+				match guard.process() {
+					Ok(future) => future.await,
+					Err(error) => guard.fail(&error),
+				}
+			}
+
+			if let Some(entry) = entries.write().await.get_mut(&entry_id) {
+				entry.completed_at.get_or_insert_with(Instant::now);
+			}
+		});
+
+		Ok(id)
+	}
+
+	pub async fn wait_for(&self, id: String) -> Result<Response, Error> {
+		let prediction = self.prediction(&id).await?;
+		loop {
+			let result = prediction.read().await.wait_for(id.clone()).await;
+			match result {
+				// Still queued: its worker hasn't taken the write lock and
+				// moved it to `Processing` yet.
+				Err(Error::AlreadyRunning) => tokio::time::sleep(Duration::from_millis(10)).await,
+				result => return result,
+			}
+		}
+	}
+
+	/// Cancels a running prediction without waiting on the write lock its
+	/// worker holds for the run's duration: the cancel signal is sent
+	/// directly on the channel `Runner::run` is already selecting on.
+	///
+	/// Errors with [`Error::AlreadyComplete`] if `id` already reached a
+	/// terminal status, rather than silently reporting success.
+	pub async fn cancel(&self, id: String) -> Result<(), Error> {
+		let entries = self.entries.read().await;
+		let entry = entries.get(&id).ok_or(Error::Unknown)?;
+
+		if entry.completed_at.is_some() {
+			return Err(Error::AlreadyComplete);
+		}
+
+		entry.cancel.send(()).unwrap();
+
+		Ok(())
+	}
+
+	async fn prediction(&self, id: &str) -> Result<Arc<RwLock<Prediction>>, Error> {
+		self.entries
+			.read()
+			.await
+			.get(id)
+			.map(|entry| entry.prediction.clone())
+			.ok_or(Error::Unknown)
+	}
+
+	async fn evict_expired(&self) {
+		let ttl = self.ttl;
+		self.entries
+			.write()
+			.await
+			.retain(|_, entry| entry.completed_at.map_or(true, |at| at.elapsed() < ttl));
+	}
+
+	pub fn extension(self) -> Extension {
+		axum::Extension(Arc::new(self))
+	}
+}
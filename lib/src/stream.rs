@@ -0,0 +1,145 @@
+use std::{pin::Pin, sync::Arc};
+
+use axum::response::sse::{Event, Sse};
+use futures::{
+	future,
+	stream::{self, Stream, StreamExt},
+};
+use serde_json::Value;
+use tokio::sync::{broadcast, RwLock};
+use tokio_stream::wrappers::BroadcastStream;
+
+use crate::prediction::Response;
+
+const CHANNEL_CAPACITY: usize = 256;
+
+/// An incremental update emitted while a prediction is running.
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+	Output(Value),
+	Logs(String),
+	Completed(Response),
+}
+
+#[derive(Default)]
+struct Buffer {
+	output: Option<Value>,
+	logs: String,
+	completed: Option<Response>,
+}
+
+/// Shared source of truth for a single prediction's incremental output and
+/// logs, fanning out to both the SSE endpoint and the `Output`/`Logs`
+/// webhook deliveries.
+///
+/// Cloning a `PredictionStream` shares the same underlying channel and
+/// buffer, so the runner, the SSE handler, and the webhook dispatcher can
+/// each hold their own handle.
+#[derive(Clone)]
+pub struct PredictionStream {
+	tx: broadcast::Sender<StreamEvent>,
+	buffer: Arc<RwLock<Buffer>>,
+}
+
+impl Default for PredictionStream {
+	fn default() -> Self {
+		let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+		Self {
+			tx,
+			buffer: Arc::new(RwLock::new(Buffer::default())),
+		}
+	}
+}
+
+impl PredictionStream {
+	/// A lightweight handle a [`Runner`](crate::runner::Runner) can use to
+	/// push partial output and appended log lines while it runs.
+	pub fn sink(&self) -> Self {
+		self.clone()
+	}
+
+	pub async fn push_output(&self, output: Value) {
+		self.buffer.write().await.output = Some(output.clone());
+		let _ = self.tx.send(StreamEvent::Output(output));
+	}
+
+	pub async fn push_logs(&self, line: &str) {
+		let mut buffer = self.buffer.write().await;
+		buffer.logs.push_str(line);
+		let _ = self.tx.send(StreamEvent::Logs(line.to_string()));
+	}
+
+	pub async fn push_completed(&self, response: Response) {
+		self.buffer.write().await.completed = Some(response.clone());
+		let _ = self.tx.send(StreamEvent::Completed(response));
+	}
+
+	/// The log lines accumulated so far, for stamping onto the final
+	/// `Response` once a prediction reaches a terminal status.
+	pub async fn logs(&self) -> String {
+		self.buffer.read().await.logs.clone()
+	}
+
+	pub fn subscribe(&self) -> broadcast::Receiver<StreamEvent> {
+		self.tx.subscribe()
+	}
+
+	/// Snapshots the buffered `logs`/`output`/terminal response and
+	/// subscribes to the live channel in the same locked section, so an
+	/// event racing in between the two can't fall through the gap and be
+	/// missed by both the replay and the live stream.
+	async fn replay(&self) -> (Vec<StreamEvent>, Option<Response>, broadcast::Receiver<StreamEvent>) {
+		let buffer = self.buffer.read().await;
+		let mut events = Vec::with_capacity(2);
+		if !buffer.logs.is_empty() {
+			events.push(StreamEvent::Logs(buffer.logs.clone()));
+		}
+		if let Some(output) = &buffer.output {
+			events.push(StreamEvent::Output(output.clone()));
+		}
+		let completed = buffer.completed.clone();
+		let live = self.tx.subscribe();
+
+		(events, completed, live)
+	}
+
+	/// Builds the SSE response body for this prediction: buffered state
+	/// first, then live events as they arrive.
+	pub async fn sse(&self) -> Sse<Pin<Box<dyn Stream<Item = Result<Event, std::convert::Infallible>> + Send>>> {
+		let (mut events, completed, live) = self.replay().await;
+
+		let events: Pin<Box<dyn Stream<Item = StreamEvent> + Send>> = if let Some(completed) = completed {
+			// Already terminal: nothing will ever be broadcast for this
+			// prediction again, so replay the completed event directly
+			// instead of listening on a channel no one will send to.
+			events.push(StreamEvent::Completed(completed));
+			Box::pin(stream::iter(events))
+		} else {
+			// Stop right after the terminal event instead of waiting on
+			// the broadcast channel forever once the prediction is done.
+			let live = BroadcastStream::new(live)
+				.filter_map(|event| async { event.ok() })
+				.scan(false, |done, event| {
+					let event = if *done { None } else { Some(event) };
+					if matches!(event, Some(StreamEvent::Completed(_))) {
+						*done = true;
+					}
+					future::ready(event)
+				});
+			Box::pin(stream::iter(events).chain(live))
+		};
+
+		Sse::new(Box::pin(events.map(|event| Ok(to_event(event)))))
+	}
+}
+
+fn to_event(event: StreamEvent) -> Event {
+	match event {
+		StreamEvent::Output(output) => Event::default().event("output").json_data(output).unwrap(),
+		StreamEvent::Logs(logs) => Event::default().event("logs").data(logs),
+		StreamEvent::Completed(response) => Event::default()
+			.event("completed")
+			.json_data(response)
+			.unwrap(),
+	}
+}
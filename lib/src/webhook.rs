@@ -0,0 +1,194 @@
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+use tracing::warn;
+use url::Url;
+
+use crate::{
+	prediction::{Response, WebhookEvent},
+	stream::{PredictionStream, StreamEvent},
+};
+
+const MAX_ATTEMPTS: u32 = 5;
+const BASE_DELAY: Duration = Duration::from_secs(1);
+const BACKOFF_FACTOR: u32 = 2;
+
+/// Dispatches webhook deliveries for a single prediction's lifecycle.
+///
+/// Cloning a `Webhook` is cheap; every delivery is spawned onto its own task so
+/// callers never block on network I/O.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+	url: Url,
+	filters: Option<Vec<WebhookEvent>>,
+	secret: Option<String>,
+	client: reqwest::Client,
+}
+
+impl Webhook {
+	pub fn new(
+		url: Url,
+		filters: Option<Vec<WebhookEvent>>,
+		secret: Option<String>,
+	) -> Self {
+		Self {
+			url,
+			filters,
+			secret,
+			client: reqwest::Client::new(),
+		}
+	}
+
+	fn enabled(&self, event: WebhookEvent) -> bool {
+		match &self.filters {
+			None => true,
+			Some(filters) if filters.is_empty() => true,
+			Some(filters) => filters.contains(&event),
+		}
+	}
+
+	/// Spawns a background delivery of `response` for `event`, if the event
+	/// passes `webhook_event_filters`. Network errors and 5xx responses are
+	/// retried with exponential backoff and ultimately dropped; other error
+	/// statuses are dropped without retry. Failures never surface to the
+	/// caller.
+	pub fn notify(&self, event: WebhookEvent, response: Response) {
+		if !self.enabled(event) {
+			return;
+		}
+
+		let webhook = self.clone();
+		tokio::spawn(async move { webhook.deliver(response).await });
+	}
+
+	/// Subscribes to `stream`'s incremental output/logs and delivers the
+	/// `Output`/`Logs` webhook events from the same source of truth that
+	/// backs the SSE endpoint, stamping each delivery onto `base`.
+	pub fn follow(&self, mut base: Response, stream: PredictionStream) {
+		if !self.enabled(WebhookEvent::Output) && !self.enabled(WebhookEvent::Logs) {
+			return;
+		}
+
+		let webhook = self.clone();
+		let mut rx = stream.subscribe();
+		tokio::spawn(async move {
+			while let Ok(event) = rx.recv().await {
+				match event {
+					StreamEvent::Output(output) => {
+						base.output = Some(output);
+						webhook.notify(WebhookEvent::Output, base.clone());
+					}
+					StreamEvent::Logs(line) => {
+						base.logs.push_str(&line);
+						webhook.notify(WebhookEvent::Logs, base.clone());
+					}
+					StreamEvent::Completed(_) => break,
+				}
+			}
+		});
+	}
+
+	async fn deliver(&self, response: Response) {
+		let body = match serde_json::to_vec(&response) {
+			Ok(body) => body,
+			Err(error) => {
+				warn!(%error, "failed to serialize webhook payload");
+				return;
+			}
+		};
+
+		let mut delay = BASE_DELAY;
+		for attempt in 1..=MAX_ATTEMPTS {
+			let mut req = self
+				.client
+				.post(self.url.clone())
+				.header("Content-Type", "application/json")
+				.body(body.clone());
+
+			if let Some(signature) = self.sign(&body) {
+				req = req.header("X-Webhook-Signature", signature);
+			}
+
+			match req.send().await {
+				Ok(resp) if resp.status().is_success() => return,
+				Ok(resp) if !resp.status().is_server_error() => {
+					// Client errors aren't going to fix themselves on retry.
+					warn!(status = %resp.status(), "webhook delivery rejected");
+					return;
+				}
+				Ok(resp) => {
+					warn!(status = %resp.status(), attempt, "webhook delivery failed, retrying");
+				}
+				Err(error) => {
+					warn!(%error, attempt, "webhook delivery failed, retrying");
+				}
+			}
+
+			if attempt == MAX_ATTEMPTS {
+				warn!("giving up on webhook delivery after {attempt} attempts");
+				return;
+			}
+
+			let jitter = rand::thread_rng().gen_range(0..250);
+			tokio::time::sleep(delay + Duration::from_millis(jitter)).await;
+			delay *= BACKOFF_FACTOR;
+		}
+	}
+
+	fn sign(&self, body: &[u8]) -> Option<String> {
+		let secret = self.secret.as_ref()?;
+		let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+			.expect("HMAC accepts keys of any size");
+		mac.update(body);
+		Some(format!("sha256={}", hex::encode(mac.finalize().into_bytes())))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn webhook(filters: Option<Vec<WebhookEvent>>, secret: Option<String>) -> Webhook {
+		Webhook::new(Url::parse("https://example.com/hook").unwrap(), filters, secret)
+	}
+
+	#[test]
+	fn enabled_with_no_filters_allows_every_event() {
+		let webhook = webhook(None, None);
+		assert!(webhook.enabled(WebhookEvent::Start));
+		assert!(webhook.enabled(WebhookEvent::Completed));
+	}
+
+	#[test]
+	fn enabled_with_empty_filters_allows_every_event() {
+		let webhook = webhook(Some(Vec::new()), None);
+		assert!(webhook.enabled(WebhookEvent::Logs));
+	}
+
+	#[test]
+	fn enabled_only_for_filtered_events() {
+		let webhook = webhook(Some(vec![WebhookEvent::Completed]), None);
+		assert!(webhook.enabled(WebhookEvent::Completed));
+		assert!(!webhook.enabled(WebhookEvent::Start));
+	}
+
+	#[test]
+	fn sign_is_none_without_a_secret() {
+		let webhook = webhook(None, None);
+		assert!(webhook.sign(b"payload").is_none());
+	}
+
+	#[test]
+	fn sign_is_deterministic_and_keyed_by_secret() {
+		let a = webhook(None, Some("secret-a".to_string()));
+		let b = webhook(None, Some("secret-b".to_string()));
+
+		let signature = a.sign(b"payload").unwrap();
+		assert!(signature.starts_with("sha256="));
+		assert_eq!(signature, a.sign(b"payload").unwrap());
+		assert_ne!(signature, b.sign(b"payload").unwrap());
+	}
+}
+